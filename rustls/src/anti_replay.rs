@@ -0,0 +1,232 @@
+use crate::hash_hs::HandshakeHash;
+use ring::digest;
+use std::convert::TryInto;
+use std::mem;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Decides whether a particular 0-RTT (early data) attempt may be accepted,
+/// or should instead fall back to a full 1-RTT handshake. Implementations
+/// may be imprecise in the safe direction (false positives cost a round
+/// trip; false negatives are a security failure). Exposed as a trait so
+/// embedders can swap in a shared or distributed store.
+///
+/// Not yet wired into any server-side handling of incoming
+/// `MessagePayload::ApplicationData` sent before the handshake completes --
+/// this source tree has no such code to call `check_and_insert` from yet.
+pub trait AntiReplay: Send + Sync {
+    /// Checks `id` against recently-seen attempts and, if it's new and
+    /// `claimed_age` is within the acceptance window, records it as seen
+    /// and returns `true`. Returns `false` if `id` is a likely replay or
+    /// `claimed_age` is out of bounds.
+    fn check_and_insert(&self, id: &[u8], claimed_age: Duration, now: SystemTime) -> bool;
+}
+
+/// Computes the per-attempt identifier used to detect 0-RTT replay: the
+/// ClientHello transcript hash under the offered PSK's `alg`, combined with
+/// the client's obfuscated ticket age and PSK binder. Hashes via
+/// `HandshakeHash::fork` rather than `HandshakeHash::get_current_hash_raw`,
+/// which panics before a cipher suite has been committed to.
+pub fn early_data_identifier(
+    transcript: &HandshakeHash,
+    alg: &'static digest::Algorithm,
+    obfuscated_ticket_age: u32,
+    psk_binder: &[u8],
+) -> Vec<u8> {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(transcript.fork(alg).finish().as_ref());
+    ctx.update(&obfuscated_ticket_age.to_be_bytes());
+    ctx.update(psk_binder);
+    ctx.finish().as_ref().to_vec()
+}
+
+/// A small fixed-size bit array with `k` hash functions derived by
+/// double-hashing a single SHA-256 digest (Kirsch-Mitzenmacher), giving an
+/// approximate, fixed-memory set membership test.
+struct BloomFilter {
+    bits: Vec<bool>,
+    k: usize,
+}
+
+impl BloomFilter {
+    fn new(expected_inserts: usize, false_positive_rate: f64) -> Self {
+        let n = (expected_inserts.max(1)) as f64;
+        let p = false_positive_rate.clamp(1e-6, 0.5);
+        let m = ((-(n * p.ln())) / std::f64::consts::LN_2.powi(2)).ceil() as usize;
+        let k = (((m as f64) / n) * std::f64::consts::LN_2).round().max(1.0) as usize;
+
+        BloomFilter {
+            bits: vec![false; m.max(64)],
+            k,
+        }
+    }
+
+    fn hashes(&self, id: &[u8]) -> (u64, u64) {
+        let digest = digest::digest(&digest::SHA256, id);
+        let bytes = digest.as_ref();
+        let h1 = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
+        let h2 = u64::from_be_bytes(bytes[8..16].try_into().unwrap());
+        (h1, h2)
+    }
+
+    fn indices(&self, id: &[u8]) -> impl Iterator<Item = usize> + '_ {
+        let (h1, h2) = self.hashes(id);
+        let len = self.bits.len();
+        (0..self.k as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) as usize) % len)
+    }
+
+    fn might_contain(&self, id: &[u8]) -> bool {
+        self.indices(id).all(|idx| self.bits[idx])
+    }
+
+    fn insert(&mut self, id: &[u8]) {
+        // Collected into a Vec first since `indices` borrows `&self`.
+        let indices: Vec<usize> = self.indices(id).collect();
+        for idx in indices {
+            self.bits[idx] = true;
+        }
+    }
+}
+
+struct Window {
+    started_at: SystemTime,
+    current: BloomFilter,
+    previous: BloomFilter,
+}
+
+/// The default `AntiReplay`: a rotating pair of Bloom filters, one covering
+/// the current window and one covering the window before it, so memory
+/// stays bounded regardless of connection volume. An identifier is treated
+/// as a replay if it appears in either filter.
+pub struct BloomAntiReplay {
+    window_len: Duration,
+    max_client_hello_age: Duration,
+    expected_inserts: usize,
+    false_positive_rate: f64,
+    window: Mutex<Window>,
+}
+
+impl BloomAntiReplay {
+    /// Creates a filter covering rolling `window_len`-long periods, sized
+    /// for roughly `expected_inserts_per_window` early-data attempts with
+    /// `false_positive_rate` chance of a false collision. Attempts whose
+    /// claimed ticket age exceeds `max_client_hello_age` are always refused.
+    pub fn new(
+        window_len: Duration,
+        max_client_hello_age: Duration,
+        expected_inserts_per_window: usize,
+        false_positive_rate: f64,
+    ) -> Self {
+        let new_filter = || BloomFilter::new(expected_inserts_per_window, false_positive_rate);
+        Self {
+            window_len,
+            max_client_hello_age,
+            expected_inserts: expected_inserts_per_window,
+            false_positive_rate,
+            window: Mutex::new(Window {
+                started_at: SystemTime::now(),
+                current: new_filter(),
+                previous: new_filter(),
+            }),
+        }
+    }
+}
+
+impl BloomAntiReplay {
+    fn new_filter(&self) -> BloomFilter {
+        BloomFilter::new(self.expected_inserts, self.false_positive_rate)
+    }
+
+    /// Rotates `window` forward however many whole `window_len`s have
+    /// elapsed since `started_at`, rather than assuming at most one.
+    fn rotate(&self, window: &mut Window, now: SystemTime) {
+        let elapsed = now.duration_since(window.started_at).unwrap_or_default();
+        if elapsed < self.window_len {
+            return;
+        }
+
+        let window_len_nanos = self.window_len.as_nanos().max(1);
+        let windows_elapsed = elapsed.as_nanos() / window_len_nanos;
+
+        if windows_elapsed == 1 {
+            window.previous = mem::replace(&mut window.current, self.new_filter());
+        } else {
+            window.current = self.new_filter();
+            window.previous = self.new_filter();
+        }
+        window.started_at = now;
+    }
+}
+
+impl AntiReplay for BloomAntiReplay {
+    fn check_and_insert(&self, id: &[u8], claimed_age: Duration, now: SystemTime) -> bool {
+        if claimed_age > self.max_client_hello_age {
+            return false;
+        }
+
+        let mut window = self.window.lock().unwrap();
+        self.rotate(&mut window, now);
+
+        if window.current.might_contain(id) || window.previous.might_contain(id) {
+            return false;
+        }
+
+        window.current.insert(id);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AntiReplay, BloomAntiReplay};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn accepts_first_attempt_and_rejects_replay() {
+        let filter = BloomAntiReplay::new(
+            Duration::from_secs(60),
+            Duration::from_secs(10),
+            1000,
+            1e-5,
+        );
+        let now = SystemTime::now();
+
+        assert!(filter.check_and_insert(b"attempt-1", Duration::from_secs(1), now));
+        assert!(!filter.check_and_insert(b"attempt-1", Duration::from_secs(1), now));
+        // A distinct identifier is unaffected.
+        assert!(filter.check_and_insert(b"attempt-2", Duration::from_secs(1), now));
+    }
+
+    #[test]
+    fn rejects_attempts_outside_the_age_window() {
+        let filter = BloomAntiReplay::new(
+            Duration::from_secs(60),
+            Duration::from_secs(10),
+            1000,
+            1e-5,
+        );
+        let now = SystemTime::now();
+
+        assert!(!filter.check_and_insert(b"too-old", Duration::from_secs(11), now));
+    }
+
+    #[test]
+    fn rotating_the_window_forgets_the_oldest_attempts() {
+        let filter = BloomAntiReplay::new(
+            Duration::from_secs(60),
+            Duration::from_secs(3600),
+            1000,
+            1e-5,
+        );
+        let t0 = SystemTime::now();
+        assert!(filter.check_and_insert(b"attempt", Duration::from_secs(1), t0));
+
+        // Still within the same window: still seen as a replay.
+        let t1 = t0 + Duration::from_secs(30);
+        assert!(!filter.check_and_insert(b"attempt", Duration::from_secs(1), t1));
+
+        // Three windows later, the previous-window filter has rotated out.
+        let t2 = t0 + Duration::from_secs(200);
+        assert!(filter.check_and_insert(b"attempt", Duration::from_secs(1), t2));
+    }
+}
@@ -18,6 +18,11 @@ pub struct HandshakeHash {
     /// None before we know what hash function we're using
     ctx: Option<digest::Context>,
 
+    /// Parallel running hashes for algorithms we might still commit to,
+    /// kept while the cipher suite (and so the hash algorithm) isn't
+    /// negotiated yet.
+    speculative: Vec<digest::Context>,
+
     /// true if we need to keep all messages
     client_auth_enabled: bool,
 
@@ -31,6 +36,7 @@ impl HandshakeHash {
     pub fn new() -> HandshakeHash {
         HandshakeHash {
             ctx: None,
+            speculative: Vec::new(),
             client_auth_enabled: false,
             buffer: Vec::new(),
             override_buffer: None
@@ -41,6 +47,7 @@ impl HandshakeHash {
     pub fn new_override(static_buffer: Vec<u8>) -> HandshakeHash {
         HandshakeHash {
             ctx: None,
+            speculative: Vec::new(),
             client_auth_enabled: false,
             buffer: Vec::new(),
             override_buffer: Some(static_buffer)
@@ -61,7 +68,10 @@ impl HandshakeHash {
         self.buffer.drain(..);
     }
 
-    /// We now know what hash function the verify_data will use.
+    /// We now know what hash function the verify_data will use. Reuses a
+    /// matching speculative context if one is being tracked (see
+    /// `start_hash_speculative`) instead of rehashing the buffer, and drops
+    /// every other speculative algorithm.
     pub fn start_hash(&mut self, alg: &'static digest::Algorithm) -> bool {
         match &self.ctx {
             None => {}
@@ -76,8 +86,19 @@ impl HandshakeHash {
             }
         }
 
-        let mut ctx = digest::Context::new(alg);
-        ctx.update(&self.buffer);
+        let ctx = match self
+            .speculative
+            .iter()
+            .position(|ctx| ctx.algorithm() == alg)
+        {
+            Some(pos) => self.speculative.swap_remove(pos),
+            None => {
+                let mut ctx = digest::Context::new(alg);
+                ctx.update(&self.buffer);
+                ctx
+            }
+        };
+        self.speculative.clear();
         self.ctx = Some(ctx);
 
         // Discard buffer if we don't need it now.
@@ -87,6 +108,42 @@ impl HandshakeHash {
         true
     }
 
+    /// Starts tracking a running hash under `alg` without committing to it.
+    /// A no-op once any algorithm has been committed, or if `alg` is already
+    /// being tracked speculatively.
+    pub fn start_hash_speculative(&mut self, alg: &'static digest::Algorithm) {
+        if self.ctx.is_some() || self.speculative.iter().any(|ctx| ctx.algorithm() == alg) {
+            return;
+        }
+
+        let mut ctx = digest::Context::new(alg);
+        ctx.update(&self.buffer);
+        self.speculative.push(ctx);
+    }
+
+    /// Returns an independent running hash for `alg`, reusing the committed
+    /// or a speculative context if either already matches `alg`, otherwise
+    /// seeding a fresh one from the buffered transcript.
+    pub fn fork(&self, alg: &'static digest::Algorithm) -> digest::Context {
+        if let Some(ctx) = &self.ctx {
+            if ctx.algorithm() == alg {
+                return ctx.clone();
+            }
+        }
+
+        if let Some(ctx) = self
+            .speculative
+            .iter()
+            .find(|ctx| ctx.algorithm() == alg)
+        {
+            return ctx.clone();
+        }
+
+        let mut ctx = digest::Context::new(alg);
+        ctx.update(&self.buffer);
+        ctx
+    }
+
     /// Hash/buffer a handshake message.
     pub fn add_message(&mut self, m: &Message) -> &mut HandshakeHash {
         if let MessagePayload::Handshake(hs) = &m.payload {
@@ -98,8 +155,13 @@ impl HandshakeHash {
 
     /// Hash or buffer a byte slice.
     fn update_raw(&mut self, buf: &[u8]) -> &mut Self {
-        if let Some(ctx) = &mut self.ctx {
-            ctx.update(buf);
+        match &mut self.ctx {
+            Some(ctx) => ctx.update(buf),
+            None => {
+                for ctx in &mut self.speculative {
+                    ctx.update(buf);
+                }
+            }
         }
 
         if self.ctx.is_none() || self.client_auth_enabled {
@@ -109,18 +171,13 @@ impl HandshakeHash {
         self
     }
 
-    /// Get the hash value if we were to hash `extra` too,
-    /// using hash function `hash`.
+    /// Get the hash value if we were to hash `extra` too, using hash
+    /// function `hash`. Before commitment any `hash` covers the whole
+    /// transcript. After commitment, a `hash` other than the committed
+    /// algorithm covers the whole transcript only when client auth keeps the
+    /// buffer; otherwise it covers only `extra`.
     pub fn get_hash_given(&self, hash: &'static digest::Algorithm, extra: &[u8]) -> digest::Digest {
-        let mut ctx = match &self.ctx {
-            None => {
-                let mut ctx = digest::Context::new(hash);
-                ctx.update(&self.buffer);
-                ctx
-            }
-            Some(ctx) => ctx.clone(),
-        };
-
+        let mut ctx = self.fork(hash);
         ctx.update(extra);
         ctx.finish()
     }
@@ -206,6 +263,47 @@ mod test {
         assert_eq!(b"helloworld".to_vec(), buf);
     }
 
+    #[test]
+    fn speculative_hashing_defers_algorithm_choice() {
+        let mut hh = HandshakeHash::new();
+        hh.update_raw(b"hello");
+        hh.start_hash_speculative(&digest::SHA256);
+        hh.start_hash_speculative(&digest::SHA384);
+        hh.update_raw(b"world");
+
+        // Forking either tracked algorithm reflects the whole transcript so far.
+        let sha256 = hh.fork(&digest::SHA256).finish();
+        let sha384 = hh.fork(&digest::SHA384).finish();
+        assert_ne!(sha256.as_ref(), sha384.as_ref());
+
+        // Committing reuses the speculative context rather than rehashing.
+        hh.start_hash(&digest::SHA256);
+        let h = hh.get_current_hash();
+        let h = h.as_ref();
+        assert_eq!(h[0], 0x93);
+        assert_eq!(h[1], 0x6a);
+        assert_eq!(h[2], 0x18);
+        assert_eq!(h[3], 0x5c);
+    }
+
+    #[test]
+    fn get_hash_given_retains_buffer_until_commitment_with_client_auth() {
+        let mut hh = HandshakeHash::new();
+        hh.set_client_auth_enabled();
+        hh.update_raw(b"hello");
+        hh.update_raw(b"world");
+
+        // Before commitment, any algorithm can be derived from the buffer.
+        let sha384_before = hh.get_hash_given(&digest::SHA384, &[]);
+
+        hh.start_hash(&digest::SHA256);
+
+        // Client auth keeps the full buffer, so a non-committed algorithm
+        // can still be derived afterwards too.
+        let sha384_after = hh.get_hash_given(&digest::SHA384, &[]);
+        assert_eq!(sha384_before.as_ref(), sha384_after.as_ref());
+    }
+
     #[test]
     fn abandon() {
         let mut hh = HandshakeHash::new();
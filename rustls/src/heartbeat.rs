@@ -0,0 +1,119 @@
+use crate::msgs::heartbeat::{HeartbeatMessageType, HeartbeatPayload};
+use crate::msgs::message::Message;
+
+/// Whether the peer is allowed to send us `HeartbeatRequest`s, as negotiated
+/// by the heartbeat extension (RFC 6520 §2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatMode {
+    /// The peer may send us `HeartbeatRequest`s and we will answer them.
+    PeerAllowedToSend,
+    /// The peer must not send us `HeartbeatRequest`s; if it does anyway, we
+    /// don't respond.
+    PeerNotAllowedToSend,
+}
+
+/// Heartbeat negotiation state and the set of requests we've sent but not
+/// yet had answered.
+///
+/// Not yet wired into message dispatch: this source tree has no code path
+/// that hands an incoming `MessagePayload::Heartbeat` to `handle_request`/
+/// `handle_response`.
+#[derive(Debug)]
+pub struct HeartbeatState {
+    mode: HeartbeatMode,
+    outstanding: Vec<Vec<u8>>,
+}
+
+impl HeartbeatState {
+    pub fn new(mode: HeartbeatMode) -> Self {
+        HeartbeatState {
+            mode,
+            outstanding: Vec::new(),
+        }
+    }
+
+    /// Handle an incoming `HeartbeatRequest`, returning the response to
+    /// send, or `None` if we're not permitted to answer.
+    pub fn handle_request(&self, req: &HeartbeatPayload) -> Option<Message> {
+        if self.mode != HeartbeatMode::PeerAllowedToSend {
+            return None;
+        }
+        if req.typ != HeartbeatMessageType::HeartbeatRequest {
+            return None;
+        }
+        Some(Message::build_heartbeat_response(req))
+    }
+
+    /// Records that we've sent a `HeartbeatRequest` carrying `payload`, so a
+    /// later response can be matched against it.
+    pub fn note_sent_request(&mut self, payload: Vec<u8>) {
+        self.outstanding.push(payload);
+    }
+
+    /// Handle an incoming `HeartbeatResponse`, returning `true` if it
+    /// matches (echoes) one of our outstanding requests -- in which case
+    /// that request is no longer considered outstanding.
+    pub fn handle_response(&mut self, resp: &HeartbeatPayload) -> bool {
+        if resp.typ != HeartbeatMessageType::HeartbeatResponse {
+            return false;
+        }
+        match self.outstanding.iter().position(|p| p == &resp.payload) {
+            Some(pos) => {
+                self.outstanding.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HeartbeatMode, HeartbeatState};
+    use crate::msgs::heartbeat::{HeartbeatMessageType, HeartbeatPayload};
+    use crate::msgs::message::MessagePayload;
+
+    fn payload(typ: HeartbeatMessageType, bytes: &[u8]) -> HeartbeatPayload {
+        HeartbeatPayload {
+            typ,
+            payload: bytes.to_vec(),
+            padding: vec![0u8; 16],
+        }
+    }
+
+    #[test]
+    fn answers_requests_when_permitted() {
+        let state = HeartbeatState::new(HeartbeatMode::PeerAllowedToSend);
+        let req = payload(HeartbeatMessageType::HeartbeatRequest, b"ping");
+        let resp = state.handle_request(&req).expect("should respond");
+        match resp.payload {
+            MessagePayload::Heartbeat(hb) => {
+                assert_eq!(hb.typ, HeartbeatMessageType::HeartbeatResponse);
+                assert_eq!(hb.payload, b"ping");
+                assert_eq!(hb.padding, req.padding);
+            }
+            _ => panic!("expected a heartbeat message"),
+        }
+    }
+
+    #[test]
+    fn ignores_requests_when_not_permitted() {
+        let state = HeartbeatState::new(HeartbeatMode::PeerNotAllowedToSend);
+        let req = payload(HeartbeatMessageType::HeartbeatRequest, b"ping");
+        assert!(state.handle_request(&req).is_none());
+    }
+
+    #[test]
+    fn matches_response_to_outstanding_request() {
+        let mut state = HeartbeatState::new(HeartbeatMode::PeerAllowedToSend);
+        state.note_sent_request(b"probe-1".to_vec());
+
+        let unrelated = payload(HeartbeatMessageType::HeartbeatResponse, b"probe-2");
+        assert!(!state.handle_response(&unrelated));
+
+        let matching = payload(HeartbeatMessageType::HeartbeatResponse, b"probe-1");
+        assert!(state.handle_response(&matching));
+        // Each outstanding request is only matched once.
+        assert!(!state.handle_response(&matching));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::msgs::base::Payload;
+use crate::msgs::codec::{Codec, Reader};
+
+/// RFC 6520 §3 `HeartbeatMessageType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatMessageType {
+    HeartbeatRequest,
+    HeartbeatResponse,
+    Unknown(u8),
+}
+
+impl Codec for HeartbeatMessageType {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        let x = match self {
+            Self::HeartbeatRequest => 1,
+            Self::HeartbeatResponse => 2,
+            Self::Unknown(x) => *x,
+        };
+        bytes.push(x);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let x = u8::read(r)?;
+        Some(match x {
+            1 => Self::HeartbeatRequest,
+            2 => Self::HeartbeatResponse,
+            _ => Self::Unknown(x),
+        })
+    }
+}
+
+/// RFC 6520 §3 `HeartbeatMessage`: a declared payload plus random padding
+/// used to pad the record out to a size of the sender's choosing (for path
+/// MTU discovery).
+#[derive(Debug, Clone)]
+pub struct HeartbeatPayload {
+    pub typ: HeartbeatMessageType,
+    pub payload: Vec<u8>,
+    pub padding: Vec<u8>,
+}
+
+impl Codec for HeartbeatPayload {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        self.typ.encode(bytes);
+        (self.payload.len() as u16).encode(bytes);
+        bytes.extend_from_slice(&self.payload);
+        bytes.extend_from_slice(&self.padding);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let typ = HeartbeatMessageType::read(r)?;
+        let len = u16::read(r)? as usize;
+
+        // Heartbleed defense: `Reader::sub` fails closed on an over-long
+        // declared length instead of reading past the record.
+        let mut payload_reader = r.sub(len)?;
+        let payload = Payload::read(&mut payload_reader).0;
+        let padding = Payload::read(r).0;
+
+        Some(HeartbeatPayload {
+            typ,
+            payload,
+            padding,
+        })
+    }
+}
+
+impl HeartbeatPayload {
+    /// Size this payload and padding would occupy on the wire once encoded
+    /// (type + length prefix + payload + padding), without building it.
+    /// Used to refuse an oversize outgoing heartbeat before encoding it.
+    pub fn encoded_len(payload: &[u8], padding: &[u8]) -> usize {
+        1 + 2 + payload.len() + padding.len()
+    }
+}
@@ -9,7 +9,7 @@ use crate::msgs::enums::{ContentType, ProtocolVersion};
 use crate::msgs::handshake::HandshakeMessagePayload;
 
 use std::convert::TryFrom;
-use crate::msgs::heartbeat::HeartbeatPayload;
+use crate::msgs::heartbeat::{HeartbeatMessageType, HeartbeatPayload};
 
 #[derive(Debug, Clone)]
 pub enum MessagePayload {
@@ -77,6 +77,16 @@ impl MessagePayload {
     }
 }
 
+/// The packet-number-space "encryption levels" QUIC carries the TLS
+/// handshake in `CRYPTO` frames under (RFC 9001 §4).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Epoch {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Application,
+}
+
 /// A TLS frame, named TLSPlaintext in the standard.
 ///
 /// This type owns all memory for its interior parts. It is used to read/write from/to I/O
@@ -87,6 +97,10 @@ pub struct OpaqueMessage {
     pub typ: ContentType,
     pub version: ProtocolVersion,
     pub payload: Payload,
+
+    /// The QUIC epoch this message was produced for, via `OpaqueMessage::from_quic`.
+    /// `None` for ordinary TLS-over-TCP messages.
+    pub epoch: Option<Epoch>,
 }
 
 impl OpaqueMessage {
@@ -124,6 +138,7 @@ impl OpaqueMessage {
             typ,
             version,
             payload,
+            epoch: None,
         })
     }
 
@@ -144,16 +159,46 @@ impl OpaqueMessage {
         }
     }
 
+    /// Maximum size of a single plaintext fragment (RFC 8446 §5.1 / RFC 5246 §6.2.1).
+    pub const MAX_PLAINTEXT_PAYLOAD: u16 = 16384;
+
     /// This is the maximum on-the-wire size of a TLSCiphertext.
     /// That's 2^14 payload bytes, a header, and a 2KB allowance
     /// for ciphertext overheads.
-    const MAX_PAYLOAD: u16 = 16384 + 2048;
+    const MAX_PAYLOAD: u16 = Self::MAX_PLAINTEXT_PAYLOAD + 2048;
 
     /// Content type, version and size.
     const HEADER_SIZE: u16 = 1 + 2 + 2;
 
     /// Maximum on-wire message size.
     pub const MAX_WIRE_SIZE: usize = (Self::MAX_PAYLOAD + Self::HEADER_SIZE) as usize;
+
+    /// Converts `msg` into the representation used on a QUIC `CRYPTO`
+    /// stream: plain payload bytes tagged with `current`, the epoch active
+    /// when it was produced. Returns `None` for `ChangeCipherSpec`, the
+    /// TLS1.2 encrypted-handshake fallback, and the heartbeat extension,
+    /// none of which have a place in a `CRYPTO` stream.
+    pub fn from_quic(msg: Message, current: Epoch) -> Option<OpaqueMessage> {
+        let typ = msg.payload.content_type();
+        let payload = match msg.payload {
+            MessagePayload::ChangeCipherSpec(_)
+            | MessagePayload::TLS12EncryptedHandshake(_)
+            | MessagePayload::Heartbeat(_) => return None,
+            MessagePayload::ApplicationData(payload) => payload,
+            other => {
+                let mut buf = Vec::new();
+                other.encode(&mut buf);
+                Payload(buf)
+            }
+        };
+
+        Some(OpaqueMessage {
+            typ,
+            version: msg.version,
+            payload,
+            epoch: Some(current),
+        })
+    }
 }
 
 impl From<Message> for OpaqueMessage {
@@ -172,6 +217,7 @@ impl From<Message> for OpaqueMessage {
             typ,
             version: msg.version,
             payload,
+            epoch: None,
         }
     }
 }
@@ -209,6 +255,40 @@ impl Message {
             payload: MessagePayload::Handshake(HandshakeMessagePayload::build_key_update_notify()),
         }
     }
+
+    /// Builds a `HeartbeatRequest` carrying `payload`, padded with `padding`
+    /// (RFC 6520 §4 recommends at least 16 random bytes of padding).
+    /// Returns `None` rather than encoding a request whose declared payload
+    /// and padding wouldn't fit in a single record.
+    pub fn build_heartbeat_request(payload: Vec<u8>, padding: Vec<u8>) -> Option<Message> {
+        if HeartbeatPayload::encoded_len(&payload, &padding)
+            > OpaqueMessage::MAX_PLAINTEXT_PAYLOAD as usize
+        {
+            return None;
+        }
+
+        Some(Message {
+            version: ProtocolVersion::TLSv1_2,
+            payload: MessagePayload::Heartbeat(HeartbeatPayload {
+                typ: HeartbeatMessageType::HeartbeatRequest,
+                payload,
+                padding,
+            }),
+        })
+    }
+
+    /// Builds the `HeartbeatResponse` to `req`, echoing exactly its stated
+    /// payload and padding as RFC 6520 §4 requires.
+    pub fn build_heartbeat_response(req: &HeartbeatPayload) -> Message {
+        Message {
+            version: ProtocolVersion::TLSv1_2,
+            payload: MessagePayload::Heartbeat(HeartbeatPayload {
+                typ: HeartbeatMessageType::HeartbeatResponse,
+                payload: req.payload.clone(),
+                padding: req.padding.clone(),
+            }),
+        }
+    }
 }
 
 impl TryFrom<OpaqueMessage> for Message {
@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::msgs::base::Payload;
+use crate::msgs::enums::{ContentType, ProtocolVersion};
+use crate::msgs::message::{Message, MessagePayload, OpaqueMessage};
+
+pub use crate::msgs::message::Epoch;
+
+/// A contiguous run of bytes destined for a single epoch's `CRYPTO` stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochBytes {
+    pub epoch: Epoch,
+    pub bytes: Vec<u8>,
+}
+
+impl Message {
+    /// Encodes this message for a QUIC `CRYPTO` stream via
+    /// `OpaqueMessage::from_quic`.
+    pub fn to_quic_epoch_bytes(&self, current: Epoch) -> Option<EpochBytes> {
+        let opaque = OpaqueMessage::from_quic(self.clone(), current)?;
+        Some(EpochBytes {
+            epoch: opaque
+                .epoch
+                .expect("OpaqueMessage::from_quic always tags an epoch"),
+            bytes: opaque.payload.0,
+        })
+    }
+}
+
+/// Flushes outgoing messages into level-tagged `CRYPTO`-frame byte runs,
+/// coalescing adjacent messages that share an epoch into one run.
+pub fn flush_to_crypto_frames(messages: &[(Epoch, Message)]) -> Vec<EpochBytes> {
+    let mut out: Vec<EpochBytes> = Vec::new();
+    for (epoch, msg) in messages {
+        let flushed = match msg.to_quic_epoch_bytes(*epoch) {
+            Some(flushed) => flushed,
+            None => continue,
+        };
+        match out.last_mut() {
+            Some(last) if last.epoch == flushed.epoch => last.bytes.extend(flushed.bytes),
+            _ => out.push(flushed),
+        }
+    }
+    out
+}
+
+/// Returns the on-the-wire size (header + body) of the handshake message
+/// starting at the front of `bytes` -- the 1-byte `HandshakeType` + 3-byte
+/// big-endian length header, plus its declared body length. `None` if
+/// `bytes` doesn't yet contain a complete header and body.
+fn next_handshake_message_len(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 4 {
+        return None;
+    }
+    let body_len = ((bytes[1] as usize) << 16) | ((bytes[2] as usize) << 8) | (bytes[3] as usize);
+    let total_len = 4 + body_len;
+    if bytes.len() < total_len {
+        return None;
+    }
+    Some(total_len)
+}
+
+/// Reassembles level-tagged `CRYPTO`-frame byte runs -- already placed in
+/// offset order by the transport -- back into `Message`s. A run may contain
+/// several coalesced handshake messages, sliced apart by header length and
+/// decoded individually through `MessagePayload::new`.
+pub fn reassemble_from_crypto_frames(runs: &[EpochBytes]) -> Result<Vec<Message>, Error> {
+    let mut out = Vec::new();
+    for run in runs {
+        let mut remaining: &[u8] = &run.bytes;
+        while !remaining.is_empty() {
+            let len = next_handshake_message_len(remaining)
+                .ok_or(Error::CorruptMessagePayload(ContentType::Handshake))?;
+            let (message_bytes, rest) = remaining.split_at(len);
+            remaining = rest;
+
+            let payload = MessagePayload::new(
+                ContentType::Handshake,
+                ProtocolVersion::TLSv1_3,
+                Payload(message_bytes.to_vec()),
+            )?;
+            out.push(Message {
+                version: ProtocolVersion::TLSv1_3,
+                payload,
+            });
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::msgs::enums::{AlertDescription, AlertLevel};
+    use crate::msgs::message::MessagePayload;
+
+    fn app_data_message(bytes: &[u8]) -> Message {
+        Message {
+            version: ProtocolVersion::TLSv1_3,
+            payload: MessagePayload::ApplicationData(Payload(bytes.to_vec())),
+        }
+    }
+
+    #[test]
+    fn application_data_is_tagged_with_the_current_epoch_not_forced_application() {
+        let msg = app_data_message(b"early-data");
+        let flushed = msg
+            .to_quic_epoch_bytes(Epoch::ZeroRtt)
+            .expect("application data should flush");
+        assert_eq!(flushed.epoch, Epoch::ZeroRtt);
+        assert_eq!(flushed.bytes, b"early-data");
+    }
+
+    #[test]
+    fn alert_is_tagged_with_the_current_epoch_instead_of_dropped() {
+        let msg = Message::build_alert(AlertLevel::Fatal, AlertDescription::HandshakeFailure);
+        let flushed = msg
+            .to_quic_epoch_bytes(Epoch::Handshake)
+            .expect("alerts must be carried, not dropped");
+        assert_eq!(flushed.epoch, Epoch::Handshake);
+        assert!(!flushed.bytes.is_empty());
+    }
+
+    #[test]
+    fn change_cipher_spec_is_suppressed() {
+        let msg = Message {
+            version: ProtocolVersion::TLSv1_3,
+            payload: MessagePayload::ChangeCipherSpec(crate::msgs::ccs::ChangeCipherSpecPayload),
+        };
+        assert!(msg.to_quic_epoch_bytes(Epoch::Handshake).is_none());
+    }
+
+    #[test]
+    fn flush_coalesces_adjacent_same_epoch_runs() {
+        let messages = vec![
+            (Epoch::Application, app_data_message(b"a")),
+            (Epoch::Application, app_data_message(b"b")),
+        ];
+        let runs = flush_to_crypto_frames(&messages);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].bytes, b"ab");
+    }
+
+    #[test]
+    fn flush_keeps_different_epochs_in_separate_runs() {
+        let messages = vec![
+            (Epoch::ZeroRtt, app_data_message(b"early")),
+            (Epoch::Application, app_data_message(b"late")),
+        ];
+        let runs = flush_to_crypto_frames(&messages);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].epoch, Epoch::ZeroRtt);
+        assert_eq!(runs[1].epoch, Epoch::Application);
+    }
+
+    #[test]
+    fn reassemble_rejects_a_body_shorter_than_its_length_prefix() {
+        let runs = vec![EpochBytes {
+            epoch: Epoch::Handshake,
+            bytes: vec![1, 0, 0, 10, 1, 2], // declares 10 body bytes, only 2 present
+        }];
+        assert!(reassemble_from_crypto_frames(&runs).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::msgs::enums::{AlertDescription, AlertLevel};
+use crate::msgs::message::Message;
+
+/// The per-direction usage limits for a single AEAD algorithm (RFC 8446
+/// §5.5), with the write-side threshold set a bit below the hard limit to
+/// leave room for the peer's `KeyUpdate` round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUsageLimits {
+    /// Number of seals after which we request a `KeyUpdate` for the write side.
+    pub rekey_threshold: u64,
+    /// Number of consecutive open failures after which the connection must
+    /// be aborted rather than continuing to feed the AEAD.
+    pub integrity_limit: u64,
+}
+
+impl KeyUsageLimits {
+    /// AES-128-GCM and AES-256-GCM, rekeying well inside the ~2^24.5 record
+    /// confidentiality limit of RFC 8446 Appendix B.
+    pub const AES_GCM: KeyUsageLimits = KeyUsageLimits {
+        rekey_threshold: 1 << 23,
+        integrity_limit: 1 << 36,
+    };
+
+    /// ChaCha20-Poly1305: effectively unbounded at TLS record volumes.
+    pub const CHACHA20_POLY1305: KeyUsageLimits = KeyUsageLimits {
+        rekey_threshold: u64::MAX,
+        integrity_limit: 1 << 36,
+    };
+}
+
+/// Counts AEAD seal/open invocations for one connection's traffic keys and
+/// decides when it's time to request a key update, or to give up on a
+/// direction that keeps failing to authenticate. The counters reset across
+/// a `KeyUpdate`, independently of the record sequence number.
+///
+/// The caller owning the record layer's seal/open calls is responsible for
+/// driving this: call `note_seal`/`note_open_success`/`note_open_failure`
+/// around each AEAD invocation, send on the `Message` that `note_seal` or
+/// `note_open_failure` returns, and call `reset_write`/`reset_read` once a `KeyUpdate` (ours
+/// or the peer's) takes effect. This source tree has no record-layer
+/// connection module to wire that into yet.
+#[derive(Debug, Clone)]
+pub struct KeyUsageTracker {
+    limits: KeyUsageLimits,
+    write_count: u64,
+    write_update_requested: bool,
+    read_count: u64,
+    consecutive_auth_failures: u64,
+}
+
+impl KeyUsageTracker {
+    pub fn new(limits: KeyUsageLimits) -> Self {
+        Self {
+            limits,
+            write_count: 0,
+            write_update_requested: false,
+            read_count: 0,
+            consecutive_auth_failures: 0,
+        }
+    }
+
+    /// Call once for every `OpaqueMessage` sealed under the current write
+    /// keys. Returns a `KeyUpdate` notify the first time the write count
+    /// crosses `rekey_threshold` since the last rekey.
+    pub fn note_seal(&mut self) -> Option<Message> {
+        self.write_count += 1;
+        if !self.write_update_requested && self.write_count >= self.limits.rekey_threshold {
+            self.write_update_requested = true;
+            return Some(Message::build_key_update_notify());
+        }
+        None
+    }
+
+    /// Call once the new write keys produced by our own key update have been
+    /// installed.
+    pub fn reset_write(&mut self) {
+        self.write_count = 0;
+        self.write_update_requested = false;
+    }
+
+    /// Call for every `OpaqueMessage` successfully opened under the current
+    /// read keys.
+    pub fn note_open_success(&mut self) {
+        self.read_count += 1;
+        self.consecutive_auth_failures = 0;
+    }
+
+    /// Call when an AEAD open fails (bad tag) under the current read keys.
+    /// Returns a fatal alert to send and abort the connection with once
+    /// consecutive failures reach the cipher's integrity limit.
+    pub fn note_open_failure(&mut self) -> Option<Message> {
+        self.consecutive_auth_failures += 1;
+        if self.consecutive_auth_failures >= self.limits.integrity_limit {
+            return Some(Message::build_alert(
+                AlertLevel::Fatal,
+                AlertDescription::BadRecordMac,
+            ));
+        }
+        None
+    }
+
+    /// Call once a peer's `KeyUpdate` has been processed and the read keys
+    /// rolled forward.
+    pub fn reset_read(&mut self) {
+        self.read_count = 0;
+        self.consecutive_auth_failures = 0;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn write_count(&self) -> u64 {
+        self.write_count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeyUsageLimits, KeyUsageTracker};
+    use crate::msgs::message::MessagePayload;
+
+    #[test]
+    fn requests_key_update_once_threshold_crossed() {
+        let mut tracker = KeyUsageTracker::new(KeyUsageLimits {
+            rekey_threshold: 3,
+            integrity_limit: 10,
+        });
+
+        assert!(tracker.note_seal().is_none());
+        assert!(tracker.note_seal().is_none());
+        let update = tracker.note_seal().expect("threshold crossed");
+        assert!(matches!(update.payload, MessagePayload::Handshake(_)));
+
+        // Further seals before the rekey completes must not re-request.
+        assert!(tracker.note_seal().is_none());
+
+        tracker.reset_write();
+        assert_eq!(tracker.write_count(), 0);
+    }
+
+    #[test]
+    fn aborts_after_consecutive_open_failures() {
+        let mut tracker = KeyUsageTracker::new(KeyUsageLimits {
+            rekey_threshold: u64::MAX,
+            integrity_limit: 2,
+        });
+
+        assert!(tracker.note_open_failure().is_none());
+        let alert = tracker.note_open_failure().expect("integrity limit hit");
+        assert!(matches!(alert.payload, MessagePayload::Alert(_)));
+    }
+
+    #[test]
+    fn successful_open_resets_failure_streak() {
+        let mut tracker = KeyUsageTracker::new(KeyUsageLimits {
+            rekey_threshold: u64::MAX,
+            integrity_limit: 2,
+        });
+
+        assert!(tracker.note_open_failure().is_none());
+        tracker.note_open_success();
+        assert!(tracker.note_open_failure().is_none());
+    }
+}